@@ -5,6 +5,9 @@ async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
     let app_state = system::AppState::new()?;
+    let persist_task = app_state.spawn_persist_task();
+    let persisted_state = app_state.clone();
+    let shutdown_token = app_state.shutdown_token();
 
     let addr = std::env::var("HOST").unwrap_or("127.0.0.1".to_string());
     let port = std::env::var("PORT").unwrap_or("3000".to_string());
@@ -33,7 +36,52 @@ async fn main() -> anyhow::Result<()> {
         // Allow credentials (cookies, etc.)
         .allow_credentials(true);
 
-    axum::serve(listener, app_state.router().layer(cors)).await?;
+    // `/message/stream` is an SSE response; compressing it would buffer chunks
+    // and defeat the point of a live stream, so it's excluded by content type.
+    let compression = tower_http::compression::CompressionLayer::new().compress_when(
+        tower_http::compression::predicate::DefaultPredicate::new().and(
+            tower_http::compression::predicate::NotForContentType::const_new("text/event-stream"),
+        ),
+    );
+
+    axum::serve(listener, app_state.router().layer(cors).layer(compression))
+        .with_graceful_shutdown(shutdown_signal(shutdown_token))
+        .await?;
+
+    // Stop the periodic flush before the final one, so they can't race each other.
+    if let Some(handle) = persist_task {
+        handle.abort();
+    }
+
+    if let Err(err) = persisted_state.persist().await {
+        tracing::error!(%err, "failed to persist message queue on shutdown");
+    }
 
     Ok(())
 }
+
+/// Resolves once either Ctrl+C or SIGTERM is received; the server then stops
+/// accepting new connections and lets in-flight requests finish.
+/// Also cancels `shutdown_token`, which tells long-lived responses (the SSE
+/// stream) to end instead of holding graceful shutdown open indefinitely.
+async fn shutdown_signal(shutdown_token: tokio_util::sync::CancellationToken) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received Ctrl+C, shutting down"),
+        _ = terminate => tracing::info!("received SIGTERM, shutting down"),
+    }
+
+    shutdown_token.cancel();
+}
@@ -1,14 +1,45 @@
 use std::sync::Arc;
 
 use anyhow::Context;
-use axum::{extract::State, response::IntoResponse};
-use tokio::sync::RwLock;
+use axum::{
+    extract::State,
+    response::{
+        sse::{Event, Sse},
+        IntoResponse,
+    },
+};
+use futures_util::StreamExt;
+use tokio::sync::{broadcast, RwLock, Semaphore};
+use tokio_util::sync::CancellationToken;
 
+mod auth;
+mod metrics;
+mod persistence;
+mod ratelimit;
 mod types;
 
+use auth::{AuthContext, AuthMode, KeyRegistry};
+use metrics::Metrics;
+use ratelimit::RateLimiter;
+
+/// Events pushed to `/message/stream` subscribers.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Message(Message),
+    Clear,
+}
+
 #[derive(Debug, Clone)]
 pub struct AppState {
     messages: Arc<RwLock<(Vec<Message>, time::PrimitiveDateTime)>>,
+    broadcaster: broadcast::Sender<StreamEvent>,
+    metrics: Arc<Metrics>,
+    byte_budget: Arc<Semaphore>,
+    keys: Arc<KeyRegistry>,
+    rate_limiter: Arc<RateLimiter>,
+    shutdown: CancellationToken,
+    persist_lock: Arc<tokio::sync::Mutex<()>>,
     config: Config,
 }
 
@@ -18,7 +49,14 @@ pub struct Config {
     pub max_message_size: usize,
     pub max_author_size: usize,
     pub max_age: time::Duration,
+    pub max_total_bytes: usize,
     pub key: String,
+    pub state_file: Option<std::path::PathBuf>,
+    pub persist_interval: Option<std::time::Duration>,
+    pub auth_mode: AuthMode,
+    pub signature_keys_dir: Option<std::path::PathBuf>,
+    pub rate_window: time::Duration,
+    pub rate_limit: usize,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -44,33 +82,135 @@ impl AppState {
                     .unwrap_or("5".to_string())
                     .parse()?,
             ),
+            max_total_bytes: std::env::var("MAX_TOTAL_BYTES")
+                .unwrap_or("1048576".to_string())
+                .parse()?,
             key: std::env::var("API_KEY").context("`API_KEY` not present")?,
+            state_file: std::env::var("STATE_FILE").ok().map(std::path::PathBuf::from),
+            persist_interval: std::env::var("PERSIST_INTERVAL_SECS")
+                .ok()
+                .map(|value| value.parse().map(std::time::Duration::from_secs))
+                .transpose()?,
+            auth_mode: std::env::var("AUTH_MODE")
+                .unwrap_or("apikey".to_string())
+                .parse()?,
+            signature_keys_dir: std::env::var("SIGNATURE_KEYS_DIR")
+                .ok()
+                .map(std::path::PathBuf::from),
+            rate_window: time::Duration::seconds(
+                std::env::var("RATE_WINDOW").unwrap_or("60".to_string()).parse()?,
+            ),
+            rate_limit: std::env::var("RATE_LIMIT")
+                .unwrap_or("20".to_string())
+                .parse()?,
         };
+        let (broadcaster, _) = broadcast::channel(128);
+
+        let keys = match (config.auth_mode, config.signature_keys_dir.as_deref()) {
+            (AuthMode::Signature, Some(dir)) => KeyRegistry::load(dir)?,
+            (AuthMode::Signature, None) => {
+                anyhow::bail!("`SIGNATURE_KEYS_DIR` required when `AUTH_MODE=signature`")
+            }
+            (AuthMode::ApiKey, _) => KeyRegistry::default(),
+        };
+
+        let (mut queue, last) = config
+            .state_file
+            .as_deref()
+            .and_then(|path| persistence::load(path, &config))
+            .unwrap_or_else(|| (Vec::new(), get_now()));
+
+        // The operator may have lowered `MAX_TOTAL_BYTES` since the snapshot was
+        // written; drop the oldest messages until it fits rather than refusing to start.
+        let mut used: usize = queue.iter().map(|m| m.message.len()).sum();
+        if used > config.max_total_bytes {
+            tracing::warn!(
+                used,
+                budget = config.max_total_bytes,
+                "restored snapshot exceeds MAX_TOTAL_BYTES, dropping oldest messages to fit"
+            );
+            while used > config.max_total_bytes && !queue.is_empty() {
+                used -= queue.remove(0).message.len();
+            }
+        }
+
+        let byte_budget = Semaphore::new(config.max_total_bytes);
+        byte_budget
+            .try_acquire_many(used as u32)
+            .expect("queue was just trimmed to fit the budget")
+            .forget();
+
         Ok(Self {
-            messages: Arc::new(RwLock::new((Vec::new(), get_now()))),
+            messages: Arc::new(RwLock::new((queue, last))),
+            broadcaster,
+            metrics: Arc::new(Metrics::new()?),
+            byte_budget: Arc::new(byte_budget),
+            keys: Arc::new(keys),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            shutdown: CancellationToken::new(),
+            persist_lock: Arc::new(tokio::sync::Mutex::new(())),
             config,
         })
     }
 
+    /// A token that cancels once shutdown begins, so long-lived responses
+    /// (the SSE stream) know to end instead of blocking graceful shutdown forever.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Writes the current queue to `STATE_FILE`, if configured. A no-op otherwise.
+    /// Serialized via `persist_lock` so the periodic task and the final
+    /// shutdown flush can never write the snapshot at the same time.
+    pub async fn persist(&self) -> anyhow::Result<()> {
+        let Some(path) = self.config.state_file.as_deref() else {
+            return Ok(());
+        };
+
+        let _guard = self.persist_lock.lock().await;
+        let (queue, last) = &*self.messages.read().await;
+        persistence::save(path, &self.config, queue, *last)
+    }
+
+    /// If `PERSIST_INTERVAL_SECS` is configured, periodically flushes the
+    /// queue to `STATE_FILE` in the background. Returns a handle the caller
+    /// should abort before doing a final flush on shutdown, so that flush
+    /// can't race a tick that's already in flight.
+    pub fn spawn_persist_task(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let interval = self.config.persist_interval?;
+        if self.config.state_file.is_none() {
+            return None;
+        }
+
+        let state = self.clone();
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(err) = state.persist().await {
+                    tracing::error!(%err, "failed to persist message queue");
+                }
+            }
+        });
+
+        Some(handle)
+    }
+
     pub fn router(self) -> axum::Router<()> {
+        let auth_context = AuthContext {
+            config: self.config.clone(),
+            keys: self.keys.clone(),
+        };
+
         axum::Router::new()
             .route("/message", axum::routing::post(add_message))
             .route("/message", axum::routing::get(get_messages))
+            .route("/message/stream", axum::routing::get(stream_messages))
             .layer(axum::middleware::from_fn_with_state(
-                self.config.clone(),
-                async |State(state): State<Config>,
-                       req: axum::extract::Request,
-                       next: axum::middleware::Next| {
-                    let header = req
-                        .headers()
-                        .get("x-api-key")
-                        .and_then(|inner| inner.to_str().ok());
-                    match header {
-                        Some(value) if value == state.key => next.run(req).await,
-                        _ => axum::http::StatusCode::UNAUTHORIZED.into_response(),
-                    }
-                },
+                auth_context,
+                auth::auth_middleware,
             ))
+            .route("/metrics", axum::routing::get(get_metrics))
             .route("/health", axum::routing::get(health))
             .with_state(self)
     }
@@ -80,22 +220,61 @@ async fn health() -> &'static str {
     "ok"
 }
 
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    match state.metrics.gather() {
+        Ok(body) => (
+            axum::http::StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            body,
+        )
+            .into_response(),
+        Err(err) => {
+            tracing::error!(%err, "failed to gather metrics");
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 async fn add_message(
     State(state): State<AppState>,
+    verified: Option<axum::Extension<auth::VerifiedAuthor>>,
     axum::Json(message): axum::Json<Message>,
-) -> axum::http::StatusCode {
+) -> axum::response::Response {
+    if let Some(axum::Extension(auth::VerifiedAuthor(verified_author))) = &verified {
+        if *verified_author != message.author {
+            tracing::error!(claimed = %message.author, verified = %verified_author, "author does not match signature key identity");
+            return axum::http::StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+
     let mut messages = state.messages.write().await;
 
-    let output = match insert_message(&state.config, &mut messages, message) {
-        Ok(()) => axum::http::StatusCode::CREATED,
-        Err(status) => status,
-    };
+    let result = insert_message(
+        &state.config,
+        &state.broadcaster,
+        &state.byte_budget,
+        &state.rate_limiter,
+        &mut messages,
+        message,
+    );
 
-    if output.is_success() {
-        tracing::debug!(count = messages.0.len(), "added message");
-    }
+    state.metrics.queue_len.set(messages.0.len() as i64);
+    state
+        .metrics
+        .bytes_in_use
+        .set((state.config.max_total_bytes - state.byte_budget.available_permits()) as i64);
 
-    output
+    match result {
+        Ok(()) => {
+            state.metrics.accepted.inc();
+            tracing::debug!(count = messages.0.len(), "added message");
+            axum::http::StatusCode::CREATED.into_response()
+        }
+        Err(err) => {
+            state.metrics.rejected.inc();
+            err.into_response()
+        }
+    }
 }
 
 async fn get_messages(State(state): State<AppState>) -> axum::Json<Vec<Message>> {
@@ -106,32 +285,109 @@ async fn get_messages(State(state): State<AppState>) -> axum::Json<Vec<Message>>
     axum::Json(messages.0.clone())
 }
 
+/// Streams every accepted message (and queue-clear notifications) as they happen.
+async fn stream_messages(
+    State(state): State<AppState>,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, std::convert::Infallible>>> {
+    let receiver = state.broadcaster.subscribe();
+    let stream = tokio_stream::wrappers::BroadcastStream::new(receiver)
+        .filter_map(|event| async {
+            match event {
+                Ok(event) => Some(Ok(Event::default().json_data(event).expect("serializable"))),
+                Err(tokio_stream::wrappers::errors::BroadcastStreamRecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "sse subscriber lagged, skipping missed messages");
+                    None
+                }
+            }
+        })
+        // Subscribers are expected to stay connected indefinitely, so without this
+        // a single live stream would block graceful shutdown from ever completing.
+        .take_until(state.shutdown.clone().cancelled_owned());
+
+    Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default())
+}
+
+/// Why a message was rejected. Kept distinct from a bare `StatusCode` so the
+/// rate-limited case can carry the `Retry-After` value through to the response.
+enum InsertError {
+    TooLarge,
+    TooManyFromAuthor,
+    BudgetExhausted,
+    RateLimited(time::Duration),
+}
+
+impl IntoResponse for InsertError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            InsertError::TooLarge => axum::http::StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+            InsertError::TooManyFromAuthor => {
+                axum::http::StatusCode::TOO_MANY_REQUESTS.into_response()
+            }
+            InsertError::BudgetExhausted => {
+                axum::http::StatusCode::INSUFFICIENT_STORAGE.into_response()
+            }
+            InsertError::RateLimited(retry_after) => (
+                axum::http::StatusCode::TOO_MANY_REQUESTS,
+                [(
+                    axum::http::header::RETRY_AFTER,
+                    retry_after.whole_seconds().max(0).to_string(),
+                )],
+            )
+                .into_response(),
+        }
+    }
+}
+
 fn insert_message(
     config: &Config,
+    broadcaster: &broadcast::Sender<StreamEvent>,
+    byte_budget: &Semaphore,
+    rate_limiter: &RateLimiter,
     (queue, last): &mut (Vec<Message>, time::PrimitiveDateTime),
     message: Message,
-) -> Result<(), axum::http::StatusCode> {
+) -> Result<(), InsertError> {
     if get_now() - *last > config.max_age {
+        let freed: usize = queue.iter().map(|m| m.message.len()).sum();
+        byte_budget.add_permits(freed);
         queue.clear();
         *last = get_now();
+        let _ = broadcaster.send(StreamEvent::Clear);
     }
 
     if message.message.len() > config.max_message_size {
         tracing::error!(author = %message.author, length = message.message.len(), "message too large");
-        return Err(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+        return Err(InsertError::TooLarge);
     }
 
     if queue.iter().filter(|m| m.author == message.author).count() >= config.max_author_size {
         tracing::error!(author = %message.author, "too many messages");
-        return Err(axum::http::StatusCode::TOO_MANY_REQUESTS);
+        return Err(InsertError::TooManyFromAuthor);
     }
 
-    queue.push(message);
+    let Ok(permit) = byte_budget.try_acquire_many(message.message.len() as u32) else {
+        tracing::error!(author = %message.author, "byte budget exhausted");
+        return Err(InsertError::BudgetExhausted);
+    };
+
+    // Checked last, right before the message is actually queued, so a message
+    // rejected by any check above never consumes a slot in the author's window.
+    if let Err(retry_after) = rate_limiter.check(config, &message.author, get_now()) {
+        tracing::error!(author = %message.author, "rate limit exceeded");
+        return Err(InsertError::RateLimited(retry_after));
+    }
+
+    permit.forget();
+
+    queue.push(message.clone());
 
     if queue.len() > config.queue_size {
-        queue.remove(0);
+        let evicted = queue.remove(0);
+        byte_budget.add_permits(evicted.message.len());
     }
 
+    // Subscribers may have gone away; that's not a failure for the caller.
+    let _ = broadcaster.send(StreamEvent::Message(message));
+
     Ok(())
 }
 
@@ -0,0 +1,249 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::Engine;
+use rsa::{
+    pkcs1v15::{Signature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+    signature::Verifier,
+    RsaPublicKey,
+};
+use sha2::{Digest as _, Sha256};
+
+use super::{get_now, Config};
+
+/// How clients authenticate to the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMode {
+    ApiKey,
+    Signature,
+}
+
+impl std::str::FromStr for AuthMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "apikey" => Ok(Self::ApiKey),
+            "signature" => Ok(Self::Signature),
+            other => anyhow::bail!("unknown `AUTH_MODE` {other:?}, expected apikey|signature"),
+        }
+    }
+}
+
+/// Registered public keys, one per author, used to verify `signature` mode requests.
+#[derive(Debug, Default)]
+pub struct KeyRegistry(HashMap<String, RsaPublicKey>);
+
+impl KeyRegistry {
+    /// Loads one RSA public key (PEM) per `.pem` file in `dir`, keyed by file
+    /// stem. Files with any other extension are skipped.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let mut keys = HashMap::new();
+
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("pem") {
+                continue;
+            }
+
+            let author = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| anyhow::anyhow!("non-utf8 key file name: {path:?}"))?
+                .to_string();
+
+            let pem = std::fs::read_to_string(&path)?;
+            let key = RsaPublicKey::from_public_key_pem(&pem)
+                .map_err(|err| anyhow::anyhow!("invalid public key in {path:?}: {err}"))?;
+
+            keys.insert(author, key);
+        }
+
+        Ok(Self(keys))
+    }
+}
+
+/// Author identity bound by a verified `signature` mode request, carried as a
+/// request extension so handlers can check it against the claimed `Message.author`.
+#[derive(Debug, Clone)]
+pub struct VerifiedAuthor(pub String);
+
+/// State consumed by [`auth_middleware`], handed to `from_fn_with_state`.
+#[derive(Clone)]
+pub struct AuthContext {
+    pub config: Config,
+    pub keys: Arc<KeyRegistry>,
+}
+
+pub async fn auth_middleware(State(ctx): State<AuthContext>, req: Request, next: Next) -> Response {
+    match ctx.config.auth_mode {
+        AuthMode::ApiKey => {
+            let header = req
+                .headers()
+                .get("x-api-key")
+                .and_then(|inner| inner.to_str().ok());
+            match header {
+                Some(value) if value == ctx.config.key => next.run(req).await,
+                _ => axum::http::StatusCode::UNAUTHORIZED.into_response(),
+            }
+        }
+        AuthMode::Signature => {
+            // The signature must cover a hash of the body (via `digest`), so the
+            // body needs to be buffered here and handed back to `req` afterwards.
+            let (parts, body) = req.into_parts();
+            let body = match axum::body::to_bytes(body, usize::MAX).await {
+                Ok(body) => body,
+                Err(_) => return axum::http::StatusCode::BAD_REQUEST.into_response(),
+            };
+
+            match verify_request(&ctx.keys, &ctx.config, &parts, &body) {
+                Ok(author) => {
+                    let mut req = Request::from_parts(parts, axum::body::Body::from(body));
+                    req.extensions_mut().insert(VerifiedAuthor(author));
+                    next.run(req).await
+                }
+                Err(status) => status.into_response(),
+            }
+        }
+    }
+}
+
+struct ParsedSignature {
+    key_id: String,
+    headers: Vec<String>,
+    signature: Vec<u8>,
+}
+
+fn parse_signature_header(value: &str) -> Option<ParsedSignature> {
+    let mut key_id = None;
+    let mut headers = None;
+    let mut signature = None;
+
+    for field in value.split(',') {
+        let (name, value) = field.split_once('=')?;
+        let value = value.trim().trim_matches('"');
+        match name.trim() {
+            "keyId" => key_id = Some(value.to_string()),
+            "headers" => headers = Some(value.split(' ').map(str::to_string).collect()),
+            "signature" => {
+                signature = Some(
+                    base64::Engine::decode(&base64::engine::general_purpose::STANDARD, value)
+                        .ok()?,
+                )
+            }
+            _ => {}
+        }
+    }
+
+    Some(ParsedSignature {
+        key_id: key_id?,
+        // Per the Cavage draft, a missing `headers` param defaults to signing just `date`.
+        headers: headers.unwrap_or_else(|| vec!["date".to_string()]),
+        signature: signature?,
+    })
+}
+
+fn signing_string(headers: &[String], parts: &Parts) -> Option<String> {
+    let lines: Option<Vec<String>> = headers
+        .iter()
+        .map(|name| {
+            if name == "(request-target)" {
+                Some(format!(
+                    "(request-target): {} {}",
+                    parts.method.as_str().to_lowercase(),
+                    parts.uri.path()
+                ))
+            } else {
+                let value = parts.headers.get(name)?.to_str().ok()?;
+                Some(format!("{name}: {value}"))
+            }
+        })
+        .collect();
+
+    Some(lines?.join("\n"))
+}
+
+fn verify_request(
+    keys: &KeyRegistry,
+    config: &Config,
+    parts: &Parts,
+    body: &[u8],
+) -> Result<String, axum::http::StatusCode> {
+    let raw = parts
+        .headers
+        .get("Signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(axum::http::StatusCode::UNAUTHORIZED)?;
+
+    let parsed = parse_signature_header(raw).ok_or(axum::http::StatusCode::UNAUTHORIZED)?;
+
+    // `date` must be part of the signed headers, otherwise a captured request
+    // can be replayed with a freshly forged `Date` and still verify.
+    if !parsed.headers.iter().any(|name| name == "date") {
+        tracing::error!(key_id = %parsed.key_id, "signature does not cover the `date` header");
+        return Err(axum::http::StatusCode::UNAUTHORIZED);
+    }
+
+    // `digest` must also be signed and must match the body, otherwise the
+    // signature never actually binds the request to its `message` payload and
+    // a captured request could be replayed with a different body.
+    if !parsed.headers.iter().any(|name| name == "digest") {
+        tracing::error!(key_id = %parsed.key_id, "signature does not cover the `digest` header");
+        return Err(axum::http::StatusCode::UNAUTHORIZED);
+    }
+
+    let digest_header = parts
+        .headers
+        .get("Digest")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(axum::http::StatusCode::UNAUTHORIZED)?;
+
+    let expected_digest = format!(
+        "SHA-256={}",
+        base64::engine::general_purpose::STANDARD.encode(Sha256::digest(body))
+    );
+
+    if digest_header != expected_digest {
+        tracing::error!(key_id = %parsed.key_id, "digest header does not match request body");
+        return Err(axum::http::StatusCode::UNAUTHORIZED);
+    }
+
+    let date = parts
+        .headers
+        .get(axum::http::header::DATE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+        .ok_or(axum::http::StatusCode::UNAUTHORIZED)?;
+    let date = time::OffsetDateTime::from(date);
+    let date = time::PrimitiveDateTime::new(date.date(), date.time());
+
+    if (get_now() - date).abs() > config.max_age {
+        tracing::error!(key_id = %parsed.key_id, "signature date outside max_age window");
+        return Err(axum::http::StatusCode::UNAUTHORIZED);
+    }
+
+    let public_key = keys
+        .0
+        .get(&parsed.key_id)
+        .ok_or(axum::http::StatusCode::UNAUTHORIZED)?;
+
+    let signing_string =
+        signing_string(&parsed.headers, parts).ok_or(axum::http::StatusCode::UNAUTHORIZED)?;
+
+    let verifying_key = VerifyingKey::<Sha256>::new(public_key.clone());
+    let signature = Signature::try_from(parsed.signature.as_slice())
+        .map_err(|_| axum::http::StatusCode::UNAUTHORIZED)?;
+
+    verifying_key
+        .verify(signing_string.as_bytes(), &signature)
+        .map_err(|_| axum::http::StatusCode::UNAUTHORIZED)?;
+
+    Ok(parsed.key_id)
+}
@@ -0,0 +1,56 @@
+use prometheus::{Encoder, IntCounter, IntGauge, Registry, TextEncoder};
+
+/// Prometheus registry and the counters/gauges `imchat` reports through `/metrics`.
+///
+/// `accepted`/`rejected` are deliberately NOT labeled by author: `author` is an
+/// arbitrary client-controlled string, and labeling on it would let a client
+/// blow up the registry's cardinality (and the scrape payload) without bound.
+/// Per-author abuse signal is already covered by the rate limiter.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub accepted: IntCounter,
+    pub rejected: IntCounter,
+    pub queue_len: IntGauge,
+    pub bytes_in_use: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> anyhow::Result<Self> {
+        let registry = Registry::new();
+
+        let accepted = IntCounter::new(
+            "imchat_messages_accepted_total",
+            "Number of messages accepted",
+        )?;
+        let rejected = IntCounter::new(
+            "imchat_messages_rejected_total",
+            "Number of messages rejected",
+        )?;
+        let queue_len = IntGauge::new("imchat_queue_length", "Number of messages currently queued")?;
+        let bytes_in_use = IntGauge::new(
+            "imchat_bytes_in_use",
+            "Bytes currently held by messages in the queue",
+        )?;
+
+        registry.register(Box::new(accepted.clone()))?;
+        registry.register(Box::new(rejected.clone()))?;
+        registry.register(Box::new(queue_len.clone()))?;
+        registry.register(Box::new(bytes_in_use.clone()))?;
+
+        Ok(Self {
+            registry,
+            accepted,
+            rejected,
+            queue_len,
+            bytes_in_use,
+        })
+    }
+
+    pub fn gather(&self) -> anyhow::Result<String> {
+        let metric_families = self.registry.gather();
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&metric_families, &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use super::{get_now, Config, Message};
+
+/// Snapshot format written to `STATE_FILE`. `version` lets us safely change
+/// the shape of this struct later without choking on an old file on disk.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Snapshot {
+    version: u32,
+    saved_at: time::PrimitiveDateTime,
+    max_age_minutes: i64,
+    messages: Vec<Message>,
+}
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Loads a previously saved queue, discarding it if it's from a different
+/// snapshot version or if it's already older than `max_age` would allow.
+pub fn load(path: &Path, config: &Config) -> Option<(Vec<Message>, time::PrimitiveDateTime)> {
+    let bytes = std::fs::read(path).ok()?;
+
+    let snapshot: Snapshot = match serde_json::from_slice(&bytes) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            tracing::warn!(%err, "ignoring unreadable state file");
+            return None;
+        }
+    };
+
+    if snapshot.version != SNAPSHOT_VERSION {
+        tracing::warn!(
+            found = snapshot.version,
+            expected = SNAPSHOT_VERSION,
+            "state file version mismatch, discarding"
+        );
+        return None;
+    }
+
+    if get_now() - snapshot.saved_at > config.max_age {
+        tracing::info!("state file is older than `max_age`, discarding");
+        return None;
+    }
+
+    tracing::info!(
+        count = snapshot.messages.len(),
+        "restored messages from state file"
+    );
+    Some((snapshot.messages, snapshot.saved_at))
+}
+
+/// Writes the queue to `path` atomically (write to a temp file, then rename)
+/// so a crash mid-write can never leave behind a corrupt snapshot.
+///
+/// Callers are expected to serialize calls to this function themselves (see
+/// `AppState::persist`'s lock) since two concurrent saves would otherwise
+/// race to create/rename the same temp path; the unique, per-call temp file
+/// name here is only a second line of defense against that.
+pub fn save(
+    path: &Path,
+    config: &Config,
+    messages: &[Message],
+    last: time::PrimitiveDateTime,
+) -> anyhow::Result<()> {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        saved_at: last,
+        max_age_minutes: config.max_age.whole_minutes(),
+        messages: messages.to_vec(),
+    };
+
+    let tmp_path = path.with_extension(format!(
+        "tmp.{}.{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp_path, serde_json::to_vec(&snapshot)?)?;
+    std::fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use super::Config;
+
+/// Per-author sliding-window rate limiter, decoupled from the display
+/// queue's `max_age` clearing so abuse protection doesn't reset globally
+/// every time the queue empties out.
+#[derive(Debug, Default)]
+pub struct RateLimiter(Mutex<HashMap<String, VecDeque<time::PrimitiveDateTime>>>);
+
+impl RateLimiter {
+    /// Records a post for `author` at `now`, pruning timestamps older than
+    /// `config.rate_window`. Returns `Err(retry_after)`, without recording
+    /// the post, when the author is already at `config.rate_limit` within the window.
+    pub fn check(
+        &self,
+        config: &Config,
+        author: &str,
+        now: time::PrimitiveDateTime,
+    ) -> Result<(), time::Duration> {
+        // A limit of 0 means "allow nothing"; nothing was ever recorded to
+        // derive a `Retry-After` from, so just make the caller wait a full window.
+        if config.rate_limit == 0 {
+            return Err(config.rate_window);
+        }
+
+        let mut authors = self.0.lock().expect("rate limiter mutex poisoned");
+
+        // `author` is client-controlled and unbounded; drop every author whose
+        // newest post has left the window, so one-off/varying authors don't linger.
+        authors.retain(|_, window| {
+            window
+                .back()
+                .is_some_and(|&newest| now - newest <= config.rate_window)
+        });
+
+        if let Some(window) = authors.get_mut(author) {
+            while window
+                .front()
+                .is_some_and(|&oldest| now - oldest > config.rate_window)
+            {
+                window.pop_front();
+            }
+
+            if window.len() >= config.rate_limit {
+                let oldest = *window.front().expect("non-empty: len >= rate_limit > 0");
+                return Err(config.rate_window - (now - oldest));
+            }
+
+            if window.is_empty() {
+                authors.remove(author);
+            }
+        }
+
+        authors.entry(author.to_string()).or_default().push_back(now);
+        Ok(())
+    }
+}